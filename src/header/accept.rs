@@ -1,10 +1,18 @@
+#[cfg(not(feature = "base64-simd"))]
 use base64;
+#[cfg(feature = "base64-simd")]
+use base64_simd;
 use header::WebSocketKey;
 use hyper;
 use hyper::header::parsing::from_one_raw_str;
 use hyper::header::{Header, HeaderFormat};
 use result::{WebSocketError, WebSocketResult};
+#[cfg(not(feature = "digest-sha1"))]
 use sha1::Sha1;
+// `sha1_digest` is the RustCrypto `sha1` crate, renamed in Cargo.toml to
+// avoid clashing with the legacy `sha1` crate used by the default backend.
+#[cfg(feature = "digest-sha1")]
+use sha1_digest::{Digest, Sha1 as DigestSha1};
 use std::fmt::{self, Debug};
 use std::str::FromStr;
 
@@ -24,41 +32,157 @@ impl FromStr for WebSocketAccept {
 	type Err = WebSocketError;
 
 	fn from_str(accept: &str) -> WebSocketResult<WebSocketAccept> {
-		match base64::decode(accept) {
-			Ok(vec) => {
-				if vec.len() != 20 {
-					return Err(WebSocketError::ProtocolError(
-						"Sec-WebSocket-Accept must be 20 bytes",
-					));
-				}
-				let mut array = [0u8; 20];
-				array[..20].clone_from_slice(&vec[..20]);
-				Ok(WebSocketAccept(array))
-			}
-			Err(_) => Err(WebSocketError::ProtocolError(
-				"Invalid Sec-WebSocket-Accept",
-			)),
-		}
+		WebSocketAccept::parse_raw(accept.as_bytes())
+	}
+}
+
+/// Derive the `Sec-WebSocket-Accept` value for a raw `Sec-WebSocket-Key`,
+/// without needing a typed `WebSocketKey`. This is the computation from
+/// RFC 6455 §1.3: base64(key) is concatenated with `MAGIC_GUID`, hashed with
+/// SHA-1, and the 20-byte digest is base64-encoded again.
+///
+/// Useful for handshake code that lives outside this crate's stream
+/// machinery (proxies, custom upgrade logic, test harnesses).
+pub fn derive_accept_key(key: &[u8]) -> String {
+	let accept_bytes = derive_accept_bytes(key);
+	b64_encode(&accept_bytes[..])
+}
+
+fn derive_accept_bytes(key: &[u8]) -> [u8; 20] {
+	let encoded_key = b64_encode(key);
+	let mut concat_key = String::with_capacity(encoded_key.len() + 36);
+	concat_key.push_str(&encoded_key[..]);
+	concat_key.push_str(MAGIC_GUID);
+	sha1_digest(concat_key.as_bytes())
+}
+
+/// Hash `data` with SHA-1, routed through the RustCrypto `Digest`-based
+/// backend when the `digest-sha1` feature is enabled (for platforms that
+/// want hardware-accelerated SHA extensions or already link that backend
+/// elsewhere) and through the default `sha1` crate otherwise.
+#[cfg(not(feature = "digest-sha1"))]
+fn sha1_digest(data: &[u8]) -> [u8; 20] {
+	let mut sha1 = Sha1::new();
+	sha1.update(data);
+	sha1.digest().bytes()
+}
+
+#[cfg(feature = "digest-sha1")]
+fn sha1_digest(data: &[u8]) -> [u8; 20] {
+	let mut hasher = DigestSha1::new();
+	hasher.update(data);
+	let result = hasher.finalize();
+	let mut out = [0u8; 20];
+	out.copy_from_slice(&result);
+	out
+}
+
+/// Base64-encode, routed through the SIMD-accelerated backend when the
+/// `base64-simd` feature is enabled and through the scalar `base64` crate
+/// otherwise. The public API (`serialize`, `derive_accept_key`, ...) is
+/// unaffected either way.
+#[cfg(not(feature = "base64-simd"))]
+fn b64_encode(bytes: &[u8]) -> String {
+	base64::encode(bytes)
+}
+
+#[cfg(feature = "base64-simd")]
+fn b64_encode(bytes: &[u8]) -> String {
+	base64_simd::STANDARD.encode_to_string(bytes)
+}
+
+/// A safe upper bound on the number of bytes `value` can decode to, without
+/// actually decoding it. Both base64 backends below index into a fixed-size
+/// output buffer *before* checking the decoded length fits, so this must be
+/// checked first: otherwise an oversized `Sec-WebSocket-Accept` value (e.g.
+/// a malformed one from a peer) makes them panic instead of returning an
+/// error.
+fn decoded_len_upper_bound(value: &[u8]) -> usize {
+	let len = value.len();
+	if len == 0 {
+		return 0;
+	}
+	let groups = len.div_ceil(4);
+	let padding = if len.is_multiple_of(4) {
+		value.iter().rev().take_while(|&&b| b == b'=').count().min(2)
+	} else {
+		0
+	};
+	(groups * 3).saturating_sub(padding)
+}
+
+/// Base64-decode `value` into `out`, returning the number of bytes written.
+/// Same scalar/SIMD split as [`b64_encode`].
+#[cfg(not(feature = "base64-simd"))]
+fn b64_decode_into(value: &[u8], out: &mut [u8; 20]) -> Result<usize, ()> {
+	if decoded_len_upper_bound(value) > out.len() {
+		return Err(());
+	}
+	base64::decode_config_slice(value, base64::STANDARD, out).map_err(|_| ())
+}
+
+#[cfg(feature = "base64-simd")]
+fn b64_decode_into(value: &[u8], out: &mut [u8; 20]) -> Result<usize, ()> {
+	if decoded_len_upper_bound(value) > out.len() {
+		return Err(());
 	}
+	base64_simd::STANDARD
+		.decode(value, base64_simd::Out::from_slice(out))
+		.map(|decoded| decoded.len())
+		.map_err(|_| ())
 }
 
 impl WebSocketAccept {
 	/// Create a new WebSocketAccept from the given WebSocketKey
 	pub fn new(key: &WebSocketKey) -> WebSocketAccept {
-		let serialized = key.serialize();
-		let mut concat_key = String::with_capacity(serialized.len() + 36);
-		concat_key.push_str(&serialized[..]);
-		concat_key.push_str(MAGIC_GUID);
-		let mut sha1 = Sha1::new();
-		sha1.update(concat_key.as_bytes());
-		let bytes = sha1.digest().bytes();
-		WebSocketAccept(bytes)
+		let WebSocketKey(key_bytes) = *key;
+		WebSocketAccept(derive_accept_bytes(&key_bytes))
 	}
 	/// Return the Base64 encoding of this WebSocketAccept
 	pub fn serialize(&self) -> String {
 		let WebSocketAccept(accept) = *self;
-		base64::encode(&accept)
+		b64_encode(&accept)
+	}
+
+	/// Decode a raw `Sec-WebSocket-Accept` header value straight into the
+	/// fixed-size digest, without an intermediate `Vec`.
+	fn parse_raw(value: &[u8]) -> WebSocketResult<WebSocketAccept> {
+		let mut array = [0u8; 20];
+		let len = b64_decode_into(value, &mut array)
+			.map_err(|_| WebSocketError::ProtocolError("Invalid Sec-WebSocket-Accept"))?;
+		if len != 20 {
+			return Err(WebSocketError::ProtocolError(
+				"Sec-WebSocket-Accept must be 20 bytes",
+			));
+		}
+		Ok(WebSocketAccept(array))
+	}
+
+	/// Returns true if this is the accept value the server should have
+	/// returned for `key`, comparing the raw digest bytes in constant time
+	/// rather than the base64 text.
+	pub fn matches(&self, key: &WebSocketKey) -> bool {
+		let WebSocketKey(key_bytes) = *key;
+		let expected = derive_accept_bytes(&key_bytes);
+		let WebSocketAccept(actual) = *self;
+		constant_time_eq(&expected, &actual)
+	}
+
+	/// Decode a raw `Sec-WebSocket-Accept` header value and verify it against
+	/// `key` in one step, without decoding into a `Vec` or re-encoding
+	/// `key`'s expected accept value as a string first.
+	pub fn from_key(raw: &[u8], key: &WebSocketKey) -> WebSocketResult<bool> {
+		let accept = WebSocketAccept::parse_raw(raw)?;
+		Ok(accept.matches(key))
+	}
+}
+
+fn constant_time_eq(a: &[u8; 20], b: &[u8; 20]) -> bool {
+	let mut diff = 0u8;
+	for i in 0..20 {
+		diff |= a[i] ^ b[i];
 	}
+	diff == 0
 }
 
 impl Header for WebSocketAccept {
@@ -128,6 +252,25 @@ mod tests {
 		});
 	}
 
+	#[test]
+	fn test_header_accept_matches() {
+		let key: WebSocketKey = FromStr::from_str("dGhlIHNhbXBsZSBub25jZQ==").unwrap();
+		let other_key: WebSocketKey = FromStr::from_str("YSBkaXN0aW5jdCBub25jZQ==").unwrap();
+		let accept = WebSocketAccept::new(&key);
+
+		assert!(accept.matches(&key));
+		assert!(!accept.matches(&other_key));
+	}
+
+	#[test]
+	fn test_header_accept_from_key() {
+		let key: WebSocketKey = FromStr::from_str("dGhlIHNhbXBsZSBub25jZQ==").unwrap();
+		let raw = b"s3pPLMBiTxaQ9kYGzzhZRbK+xOo=";
+
+		assert_eq!(WebSocketAccept::from_key(raw, &key).unwrap(), true);
+		assert!(WebSocketAccept::from_key(b"YSBzaG9ydCBub25jZQ==", &key).is_err());
+	}
+
 	#[bench]
 	fn bench_header_accept_format(b: &mut test::Bencher) {
 		let value = vec![b"s3pPLMBiTxaQ9kYGzzhZRbK+xOo=".to_vec()];
@@ -136,4 +279,22 @@ mod tests {
 			format!("{}", val.serialize());
 		});
 	}
+
+	// The benches above exercise whichever backend the `base64-simd` feature
+	// selects. The two below pin each backend explicitly so the win from
+	// enabling the feature is measurable rather than inferred.
+
+	#[cfg(not(feature = "base64-simd"))]
+	#[bench]
+	fn bench_header_accept_encode_scalar(b: &mut test::Bencher) {
+		let bytes = [0u8; 20];
+		b.iter(|| test::black_box(base64::encode(&bytes[..])));
+	}
+
+	#[cfg(feature = "base64-simd")]
+	#[bench]
+	fn bench_header_accept_encode_simd(b: &mut test::Bencher) {
+		let bytes = [0u8; 20];
+		b.iter(|| test::black_box(base64_simd::STANDARD.encode_to_string(&bytes[..])));
+	}
 }
@@ -0,0 +1,278 @@
+use hyper;
+use hyper::header::parsing::from_one_raw_str;
+use hyper::header::{Header, HeaderFormat};
+use result::{WebSocketError, WebSocketResult};
+use std::fmt::{self, Debug};
+use std::str::FromStr;
+
+/// A single offer (or selection) from a `Sec-WebSocket-Extensions` header,
+/// e.g. `permessage-deflate; server_no_context_takeover; server_max_window_bits=10`.
+#[derive(PartialEq, Clone, Debug)]
+pub struct WebSocketExtension {
+	/// The extension token, e.g. `permessage-deflate`.
+	pub name: String,
+	/// The extension's parameters, in the order they appeared. A parameter
+	/// with no `=value` (e.g. `server_no_context_takeover`) has `None`.
+	pub params: Vec<(String, Option<String>)>,
+}
+
+impl WebSocketExtension {
+	/// Create a new extension with the given name and no parameters.
+	pub fn new(name: &str) -> WebSocketExtension {
+		WebSocketExtension {
+			name: name.to_owned(),
+			params: Vec::new(),
+		}
+	}
+
+	/// Look up the value of the first parameter with the given name.
+	///
+	/// Returns `Some(None)` for a bare parameter (present but valueless),
+	/// and `None` if no parameter with this name is present at all.
+	pub fn param(&self, name: &str) -> Option<Option<&str>> {
+		self.params
+			.iter()
+			.find(|&(key, _)| key == name)
+			.map(|(_, value)| value.as_ref().map(|v| &v[..]))
+	}
+}
+
+impl fmt::Display for WebSocketExtension {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.name)?;
+		for (key, value) in &self.params {
+			match *value {
+				Some(ref value) => {
+					if needs_quoting(value) {
+						write!(f, "; {}={}", key, quote(value))?;
+					} else {
+						write!(f, "; {}={}", key, value)?;
+					}
+				}
+				None => write!(f, "; {}", key)?,
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Returns true if a parameter value cannot be emitted as a bare token and
+/// must be quoted when re-serialized.
+fn needs_quoting(value: &str) -> bool {
+	!value
+		.bytes()
+		.all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_' || b == b'.')
+}
+
+/// Wrap `value` in a quoted-string, backslash-escaping embedded `"` and `\`
+/// per RFC 2616 §2.2's `quoted-pair` grammar so the result round-trips
+/// through [`unquote`].
+fn quote(value: &str) -> String {
+	let mut quoted = String::with_capacity(value.len() + 2);
+	quoted.push('"');
+	for c in value.chars() {
+		if c == '"' || c == '\\' {
+			quoted.push('\\');
+		}
+		quoted.push(c);
+	}
+	quoted.push('"');
+	quoted
+}
+
+/// Strip surrounding quotes from a quoted-string and undo `quoted-pair`
+/// backslash-escaping. Values that aren't quoted are returned unchanged.
+fn unquote(value: &str) -> String {
+	if value.len() < 2 || !value.starts_with('"') || !value.ends_with('"') {
+		return value.to_owned();
+	}
+	let inner = &value[1..value.len() - 1];
+	let mut unquoted = String::with_capacity(inner.len());
+	let mut chars = inner.chars();
+	while let Some(c) = chars.next() {
+		if c == '\\' {
+			if let Some(escaped) = chars.next() {
+				unquoted.push(escaped);
+				continue;
+			}
+		}
+		unquoted.push(c);
+	}
+	unquoted
+}
+
+/// Split `value` on `delim`, ignoring any `delim` that appears inside a
+/// `quoted-string` (so `bar="a;b"` isn't torn apart when splitting params
+/// on `;`).
+fn split_unquoted(value: &str, delim: char) -> Vec<&str> {
+	let mut parts = Vec::new();
+	let mut in_quotes = false;
+	let mut escaped = false;
+	let mut start = 0;
+	for (i, c) in value.char_indices() {
+		if escaped {
+			escaped = false;
+			continue;
+		}
+		match c {
+			'\\' if in_quotes => escaped = true,
+			'"' => in_quotes = !in_quotes,
+			c if c == delim && !in_quotes => {
+				parts.push(&value[start..i]);
+				start = i + c.len_utf8();
+			}
+			_ => {}
+		}
+	}
+	parts.push(&value[start..]);
+	parts
+}
+
+/// Represents a Sec-WebSocket-Extensions header, a comma-separated list of
+/// extension offers or selections (see RFC 6455 §9.1).
+#[derive(PartialEq, Clone, Debug)]
+pub struct WebSocketExtensions(Vec<WebSocketExtension>);
+
+impl WebSocketExtensions {
+	/// Create a new header from the given list of offers/selections.
+	pub fn new(extensions: Vec<WebSocketExtension>) -> WebSocketExtensions {
+		WebSocketExtensions(extensions)
+	}
+
+	/// The list of extension offers/selections in this header.
+	pub fn extensions(&self) -> &[WebSocketExtension] {
+		&self.0
+	}
+}
+
+impl FromStr for WebSocketExtensions {
+	type Err = WebSocketError;
+
+	fn from_str(extensions: &str) -> WebSocketResult<WebSocketExtensions> {
+		let mut result = Vec::new();
+		for offer in split_unquoted(extensions, ',') {
+			let offer = offer.trim();
+			if offer.is_empty() {
+				continue;
+			}
+			let mut parts = split_unquoted(offer, ';').into_iter();
+			let name = match parts.next() {
+				Some(name) if !name.trim().is_empty() => name.trim().to_owned(),
+				_ => {
+					return Err(WebSocketError::ProtocolError(
+						"Sec-WebSocket-Extensions offer is missing a name",
+					))
+				}
+			};
+			let mut params = Vec::new();
+			for param in parts {
+				let param = param.trim();
+				if param.is_empty() {
+					continue;
+				}
+				match param.find('=') {
+					Some(index) => {
+						let (key, value) = param.split_at(index);
+						let value = unquote(value[1..].trim());
+						params.push((key.trim().to_owned(), Some(value)));
+					}
+					None => params.push((param.to_owned(), None)),
+				}
+			}
+			result.push(WebSocketExtension { name, params });
+		}
+		Ok(WebSocketExtensions(result))
+	}
+}
+
+impl fmt::Display for WebSocketExtensions {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let strings: Vec<String> = self.0.iter().map(|e| e.to_string()).collect();
+		write!(f, "{}", strings.join(", "))
+	}
+}
+
+impl Header for WebSocketExtensions {
+	fn header_name() -> &'static str {
+		"Sec-WebSocket-Extensions"
+	}
+
+	fn parse_header(raw: &[Vec<u8>]) -> hyper::Result<WebSocketExtensions> {
+		from_one_raw_str(raw)
+	}
+}
+
+impl HeaderFormat for WebSocketExtensions {
+	fn fmt_header(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+		write!(fmt, "{}", self)
+	}
+}
+
+#[cfg(all(feature = "nightly", test))]
+mod tests {
+	use super::*;
+	use header::Headers;
+	use hyper::header::Header;
+
+	#[test]
+	fn test_header_extensions_parse() {
+		let extensions: WebSocketExtensions = FromStr::from_str(
+			"permessage-deflate; server_no_context_takeover; server_max_window_bits=10, bob",
+		)
+		.unwrap();
+		assert_eq!(extensions.extensions().len(), 2);
+		assert_eq!(extensions.extensions()[0].name, "permessage-deflate");
+		assert_eq!(
+			extensions.extensions()[0].param("server_max_window_bits"),
+			Some(Some("10"))
+		);
+		assert_eq!(
+			extensions.extensions()[0].param("server_no_context_takeover"),
+			Some(None)
+		);
+		assert_eq!(extensions.extensions()[0].param("missing"), None);
+		assert_eq!(extensions.extensions()[1].name, "bob");
+	}
+
+	#[test]
+	fn test_header_extensions_serialize() {
+		let mut headers = Headers::new();
+		headers.set(WebSocketExtensions::new(vec![WebSocketExtension {
+			name: "permessage-deflate".to_owned(),
+			params: vec![
+				("server_no_context_takeover".to_owned(), None),
+				("server_max_window_bits".to_owned(), Some("10".to_owned())),
+			],
+		}]));
+
+		assert_eq!(
+			&headers.to_string()[..],
+			"Sec-WebSocket-Extensions: permessage-deflate; server_no_context_takeover; server_max_window_bits=10\r\n"
+		);
+	}
+
+	#[test]
+	fn test_header_extensions_quoting() {
+		let extension = WebSocketExtension {
+			name: "foo".to_owned(),
+			params: vec![("bar".to_owned(), Some("needs space".to_owned()))],
+		};
+		assert_eq!(extension.to_string(), "foo; bar=\"needs space\"");
+	}
+
+	#[test]
+	fn test_header_extensions_quoted_value_roundtrip() {
+		let extension = WebSocketExtension {
+			name: "foo".to_owned(),
+			params: vec![("bar".to_owned(), Some("a;b,c\"d\\e".to_owned()))],
+		};
+		let serialized = extension.to_string();
+		let extensions: WebSocketExtensions =
+			FromStr::from_str(&serialized).unwrap();
+
+		assert_eq!(
+			extensions.extensions()[0].param("bar"),
+			Some(Some("a;b,c\"d\\e"))
+		);
+	}
+}
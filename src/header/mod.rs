@@ -0,0 +1,7 @@
+//! Structs representing headers relevant in a WebSocket context
+
+pub use self::accept::{derive_accept_key, WebSocketAccept};
+pub use self::extensions::{WebSocketExtension, WebSocketExtensions};
+
+mod accept;
+mod extensions;